@@ -0,0 +1,70 @@
+//! Base32 encoding for vertex/edge ids, used when displaying or sharing
+//! ids outside the database (see `vertex::Vertex::content_hash` and
+//! `GraphTraversalSource::add_content_addressed_vertex`).
+
+/// Custom unpadded alphabet used for id display.
+const ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// Number of base32 characters needed to round-trip a 64-bit id.
+const ENCODED_LEN: usize = 13;
+
+/// Encodes an id as a fixed-width, uppercase base32 string.
+pub fn encode_id(id: usize) -> String {
+    let mut remaining = id as u64;
+    let mut chars = [b'A'; ENCODED_LEN];
+    for slot in chars.iter_mut().rev() {
+        *slot = ALPHABET[(remaining & 0x1f) as usize];
+        remaining >>= 5;
+    }
+    String::from_utf8(chars.to_vec()).unwrap()
+}
+
+/// Decodes a base32 string produced by `encode_id`. Input is normalized
+/// from lowercase to uppercase first, so user-typed references are
+/// accepted case-insensitively.
+pub fn decode_id(input: &str) -> Option<usize> {
+    let normalized = input.to_ascii_uppercase();
+    if normalized.len() != ENCODED_LEN {
+        return None;
+    }
+
+    let mut value: u128 = 0;
+    for c in normalized.bytes() {
+        let digit = ALPHABET.iter().position(|&a| a == c)? as u128;
+        value = (value << 5) | digit;
+    }
+
+    if value > u64::MAX as u128 {
+        return None;
+    }
+    Some(value as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_ids() {
+        for id in [0, 1, 42, usize::MAX] {
+            let encoded = encode_id(id);
+            assert_eq!(decode_id(&encoded), Some(id));
+        }
+    }
+
+    #[test]
+    fn decode_accepts_lowercase() {
+        let encoded = encode_id(123456789);
+        assert_eq!(decode_id(&encoded.to_ascii_lowercase()), Some(123456789));
+    }
+
+    #[test]
+    fn decode_rejects_wrong_length() {
+        assert_eq!(decode_id("AB"), None);
+    }
+
+    #[test]
+    fn decode_rejects_invalid_characters() {
+        assert_eq!(decode_id("001234567890!"), None);
+    }
+}
@@ -0,0 +1,342 @@
+use crate::storage::Storage;
+use crate::transaction::Transaction;
+use crate::vertex::Vertex;
+use crate::{
+    adj_in_key, adj_out_key, base32, create_edge_key, DirtyEntry, GraphTraversalSource,
+    TraversalContext,
+};
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, VecDeque};
+use std::hash::{Hash, Hasher};
+use std::rc::Rc;
+
+pub const KEY_PREFIX: &str = "edg_";
+
+#[derive(Debug, Serialize, Deserialize, Clone, Eq, PartialEq)]
+pub struct Edge {
+    id: usize,
+    pub label: String,
+    out_v: usize,
+    in_v: usize,
+    pub prop: HashMap<String, String>,
+}
+
+impl Edge {
+    pub(crate) fn new<S: ToString>(id: usize, label: S, out_v: usize, in_v: usize) -> Edge {
+        Edge {
+            id,
+            label: label.to_string(),
+            out_v,
+            in_v,
+            prop: HashMap::new(),
+        }
+    }
+
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    pub fn out_v(&self) -> usize {
+        self.out_v
+    }
+
+    pub fn in_v(&self) -> usize {
+        self.in_v
+    }
+
+    /// A content-addressed display identity derived from this edge's
+    /// label, endpoints, and properties, base32-encoded; the edge
+    /// counterpart to `Vertex::content_hash`. See
+    /// `GraphTraversalSource::add_content_addressed_edge`.
+    pub fn content_hash(&self) -> String {
+        base32::encode_id(content_address(&self.label, self.out_v, self.in_v, &self.prop))
+    }
+}
+
+/// Derives a stable id from an edge's label, endpoints, and sorted
+/// properties, used by the content-addressed identity mode.
+pub(crate) fn content_address(
+    label: &str,
+    out_v: usize,
+    in_v: usize,
+    prop: &HashMap<String, String>,
+) -> usize {
+    let mut entries: Vec<_> = prop.iter().collect();
+    entries.sort();
+
+    let mut hasher = DefaultHasher::new();
+    label.hash(&mut hasher);
+    out_v.hash(&mut hasher);
+    in_v.hash(&mut hasher);
+    for (key, value) in entries {
+        key.hash(&mut hasher);
+        value.hash(&mut hasher);
+    }
+    hasher.finish() as usize
+}
+
+/// Builder spawned by `add_edge` that requires an endpoint in each direction
+/// before it can be resolved with `next()`. Constructed standalone (via
+/// `GraphTraversalSource::add_edge`), it commits itself as soon as the edge
+/// is built; constructed from a `Transaction`, it only accumulates into that
+/// transaction's pending writes.
+pub struct AddEdgeTraversal<'a, S: Storage> {
+    source: &'a GraphTraversalSource<S>,
+    label: String,
+    out_v: Option<usize>,
+    in_v: Option<usize>,
+    produced: bool,
+    auto_commit: bool,
+    content_addressed: bool,
+    context: Rc<RefCell<TraversalContext<'a, S>>>,
+}
+
+impl<'a, S: Storage> AddEdgeTraversal<'a, S> {
+    pub(crate) fn new(
+        source: &'a GraphTraversalSource<S>,
+        label: String,
+        context: Option<Rc<RefCell<TraversalContext<'a, S>>>>,
+    ) -> AddEdgeTraversal<'a, S> {
+        Self::new_with_mode(source, label, context, false)
+    }
+
+    /// Like `new`, but the produced edge is registered under its content
+    /// hash; see `GraphTraversalSource::add_content_addressed_edge`.
+    pub(crate) fn new_content_addressed(
+        source: &'a GraphTraversalSource<S>,
+        label: String,
+        context: Option<Rc<RefCell<TraversalContext<'a, S>>>>,
+    ) -> AddEdgeTraversal<'a, S> {
+        Self::new_with_mode(source, label, context, true)
+    }
+
+    fn new_with_mode(
+        source: &'a GraphTraversalSource<S>,
+        label: String,
+        context: Option<Rc<RefCell<TraversalContext<'a, S>>>>,
+        content_addressed: bool,
+    ) -> AddEdgeTraversal<'a, S> {
+        let auto_commit = context.is_none();
+        let context =
+            context.unwrap_or_else(|| Rc::new(RefCell::new(TraversalContext::new(&source.storage))));
+
+        AddEdgeTraversal {
+            source,
+            label,
+            out_v: None,
+            in_v: None,
+            produced: false,
+            auto_commit,
+            content_addressed,
+            context,
+        }
+    }
+
+    /// Sets the tail vertex of the edge.
+    pub fn from(mut self, out_v: usize) -> Self {
+        self.out_v = Some(out_v);
+        self
+    }
+
+    /// Sets the head vertex of the edge.
+    pub fn to(mut self, in_v: usize) -> Self {
+        self.in_v = Some(in_v);
+        self
+    }
+
+    /// Resolves `hash` to an edge id, preferring a mapping registered
+    /// earlier in this traversal's context before falling back to one
+    /// already committed to storage.
+    fn lookup_hash(&self, hash: &str) -> Option<usize> {
+        if let Some(entry) = self.context.borrow().edge_hashes.get(hash) {
+            return Some(entry.entry);
+        }
+        self.source.resolve_edge_hash(hash)
+    }
+}
+
+impl<'a, S: Storage> Iterator for AddEdgeTraversal<'a, S> {
+    type Item = Edge;
+
+    /// # Panics
+    ///
+    /// If this traversal was constructed standalone (via
+    /// `GraphTraversalSource::add_edge`/`add_content_addressed_edge`) it
+    /// commits itself here, and panics if that commit fails to write to
+    /// storage; see those methods. A traversal constructed from a
+    /// `Transaction` does not commit in `next()`, so it never panics here.
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.produced {
+            return None;
+        }
+        self.produced = true;
+
+        let out_v = self.out_v.expect("add_edge requires from(..)");
+        let in_v = self.in_v.expect("add_edge requires to(..)");
+
+        let hash = self
+            .content_addressed
+            .then(|| base32::encode_id(content_address(&self.label, out_v, in_v, &HashMap::new())));
+
+        if let Some(hash) = &hash {
+            if let Some(id) = self.lookup_hash(hash) {
+                if let Some(edge) = crate::fetch_edge(&self.context, id) {
+                    return Some(edge);
+                }
+            }
+        }
+
+        let id = self.source.new_id();
+        let edge = Edge::new(id, &self.label, out_v, in_v);
+
+        {
+            let mut context = self.context.borrow_mut();
+            if let Some(hash) = hash {
+                context.edge_hashes.insert(hash, DirtyEntry::new(id));
+            }
+            context.edges.insert(id, DirtyEntry::new(edge.clone()));
+            context
+                .adjacency
+                .insert(adj_out_key(out_v, id), DirtyEntry::new(id));
+            context
+                .adjacency
+                .insert(adj_in_key(in_v, id), DirtyEntry::new(id));
+        }
+
+        if self.auto_commit {
+            Transaction {
+                source: self.source,
+                context: Rc::clone(&self.context),
+            }
+            .commit()
+            .expect("failed to commit add_edge");
+        }
+
+        Some(edge)
+    }
+}
+
+/// Spawns a traversal over all edges.
+pub struct EdgeTraversal<'a, S: Storage> {
+    pub(crate) prefix_search: S::Iter<'a>,
+    pub(crate) label: Option<&'a str>,
+    pub(crate) _context: Rc<RefCell<TraversalContext<'a, S>>>,
+}
+
+impl<'a, S: Storage> Iterator for EdgeTraversal<'a, S> {
+    type Item = Edge;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(label) = self.label {
+            for (_, value) in self.prefix_search.by_ref() {
+                let value: Edge = bincode::deserialize(&value).unwrap();
+                if value.label == label {
+                    return Some(value);
+                }
+            }
+            None
+        } else {
+            let (_, value) = self.prefix_search.next()?;
+            Some(bincode::deserialize(&value).unwrap())
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub(crate) enum Direction {
+    Out,
+    In,
+    Both,
+}
+
+/// Resolves the vertices adjacent to each vertex produced by `source`,
+/// walking the `adj_out_`/`adj_in_` index rather than scanning all edges.
+pub struct AdjacentVertexTraversal<'a, S: Storage> {
+    source: Box<dyn Iterator<Item = Vertex> + 'a>,
+    direction: Direction,
+    label: &'a str,
+    context: Rc<RefCell<TraversalContext<'a, S>>>,
+    buffer: VecDeque<Vertex>,
+}
+
+impl<'a, S: Storage + 'a> AdjacentVertexTraversal<'a, S> {
+    pub(crate) fn new(
+        source: Box<dyn Iterator<Item = Vertex> + 'a>,
+        direction: Direction,
+        label: &'a str,
+        context: Rc<RefCell<TraversalContext<'a, S>>>,
+    ) -> AdjacentVertexTraversal<'a, S> {
+        AdjacentVertexTraversal {
+            source,
+            direction,
+            label,
+            context,
+            buffer: VecDeque::new(),
+        }
+    }
+
+    fn load_adjacent(&mut self, vertex_id: usize) {
+        let prefixes: Vec<(String, bool)> = match self.direction {
+            Direction::Out => vec![(adj_out_prefix(vertex_id), true)],
+            Direction::In => vec![(adj_in_prefix(vertex_id), false)],
+            Direction::Both => vec![
+                (adj_out_prefix(vertex_id), true),
+                (adj_in_prefix(vertex_id), false),
+            ],
+        };
+
+        for (prefix, is_out) in prefixes {
+            let context = self.context.borrow();
+            let matches: Vec<_> = context.storage.prefix_iter(prefix.as_bytes()).collect();
+            drop(context);
+
+            for (_, value) in matches {
+                let edge_id: usize = bincode::deserialize(&value).unwrap();
+                let key = create_edge_key(edge_id);
+                let bytes = match self.context.borrow().storage.get(key.as_bytes()) {
+                    Some(bytes) => bytes,
+                    None => continue,
+                };
+                let edge: Edge = bincode::deserialize(&bytes).unwrap();
+
+                if edge.label != self.label {
+                    continue;
+                }
+
+                let other_id = if is_out { edge.in_v } else { edge.out_v };
+                if let Some(vertex) = self.fetch_vertex(other_id) {
+                    self.buffer.push_back(vertex);
+                }
+            }
+        }
+    }
+
+    fn fetch_vertex(&self, id: usize) -> Option<Vertex> {
+        crate::fetch_vertex(&self.context, id)
+    }
+}
+
+impl<'a, S: Storage> Iterator for AdjacentVertexTraversal<'a, S> {
+    type Item = Vertex;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(vertex) = self.buffer.pop_front() {
+                return Some(vertex);
+            }
+
+            let vertex = self.source.next()?;
+            self.load_adjacent(vertex.id());
+        }
+    }
+}
+
+fn adj_out_prefix(vertex_id: usize) -> String {
+    format!("adj_out_{}_", vertex_id)
+}
+
+fn adj_in_prefix(vertex_id: usize) -> String {
+    format!("adj_in_{}_", vertex_id)
+}
@@ -0,0 +1,26 @@
+use crate::storage::StorageError;
+use std::error::Error;
+use std::fmt;
+
+/// The error type surfaced by fallible graph operations, such as
+/// `Transaction::commit`.
+#[derive(Debug)]
+pub struct GraphError {
+    message: String,
+}
+
+impl fmt::Display for GraphError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl Error for GraphError {}
+
+impl From<StorageError> for GraphError {
+    fn from(err: StorageError) -> GraphError {
+        GraphError {
+            message: err.to_string(),
+        }
+    }
+}
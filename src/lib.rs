@@ -1,121 +1,303 @@
-mod db;
+pub mod base32;
+pub mod edge;
+pub mod error;
+pub mod query;
+pub mod storage;
+pub mod transaction;
 pub mod vertex;
 
-use crate::vertex::{AddVertexTraversal, Vertex, VertexWithIdTraversal};
-use db::PrefixSearchIterator;
-use rocksdb::{DBWithThreadMode, SingleThreaded, DB};
+use crate::edge::{AddEdgeTraversal, Edge, EdgeTraversal};
+use crate::storage::{RocksStorage, Storage};
+use crate::transaction::Transaction;
+use crate::vertex::{AddVertexTraversal, FindByTraversal, Vertex, VertexWithIdTraversal};
 use serde::{Deserialize, Serialize};
-use std::cell::Cell;
+use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
 use std::path::Path;
 use std::rc::Rc;
 use vertex::VertexTraversal;
 
-const KEY_SYS_CONTEXT: &str = "sys_context";
+const KEY_SYS_CONTEXT: &[u8] = b"sys_context";
+const KEY_SYS_INDEXES: &[u8] = b"sys_indexes";
 
 #[derive(Debug)]
-pub struct GraphTraversalSource {
-    database: DBWithThreadMode<SingleThreaded>,
+pub struct GraphTraversalSource<S: Storage = RocksStorage> {
+    storage: S,
     context: Cell<GraphContext>,
+    indexes: RefCell<Vec<String>>,
 }
 
-impl GraphTraversalSource {
-    pub fn new<P: AsRef<Path>>(path: P) -> GraphTraversalSource {
-        let database = DB::open_default(path).unwrap();
-        let context = match database.get(KEY_SYS_CONTEXT).unwrap() {
+impl GraphTraversalSource<RocksStorage> {
+    /// Opens (or creates) a graph backed by an on-disk RocksDB database.
+    pub fn new<P: AsRef<Path>>(path: P) -> GraphTraversalSource<RocksStorage> {
+        GraphTraversalSource::with_storage(RocksStorage::open(path))
+    }
+}
+
+impl GraphTraversalSource<storage::MemoryStorage> {
+    /// Creates a graph backed by an in-memory store, useful for tests and
+    /// embedders that don't want to touch disk.
+    pub fn new_in_memory() -> GraphTraversalSource<storage::MemoryStorage> {
+        GraphTraversalSource::with_storage(storage::MemoryStorage::new())
+    }
+}
+
+impl<S: Storage> GraphTraversalSource<S> {
+    /// Creates a graph over an arbitrary `Storage` backend.
+    pub fn with_storage(storage: S) -> GraphTraversalSource<S> {
+        let context = match storage.get(KEY_SYS_CONTEXT) {
             None => {
                 let context = GraphContext { lsn: 0 };
                 let bytes = bincode::serialize(&context).unwrap();
-                database.put(KEY_SYS_CONTEXT, bytes).unwrap();
+                storage.put(KEY_SYS_CONTEXT, &bytes);
                 context
             }
             Some(x) => bincode::deserialize(&x).unwrap(),
         };
 
+        let indexes = match storage.get(KEY_SYS_INDEXES) {
+            None => Vec::new(),
+            Some(x) => bincode::deserialize(&x).unwrap(),
+        };
+
         GraphTraversalSource {
-            database,
+            storage,
             context: Cell::new(context),
+            indexes: RefCell::new(indexes),
         }
     }
 
-    /// Spawns a traversal by adding a vertex with the default label.
-    pub fn add_vertex(&self) -> AddVertexTraversal {
+    /// Opens an explicit transaction. Mutations made through it are only
+    /// persisted once `Transaction::commit` succeeds.
+    pub fn transaction(&self) -> Transaction<'_, S> {
+        Transaction {
+            source: self,
+            context: Rc::new(RefCell::new(TraversalContext::new(&self.storage))),
+        }
+    }
+
+    /// Adds a vertex with the default label, committing it immediately.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the implicit commit fails to write to storage. This
+    /// convenience method has nowhere to surface a `Result` without
+    /// breaking the traversal-returning signature every other `add_*`
+    /// method shares; use `transaction()` and its `commit()` directly if
+    /// you need to handle a storage failure instead of panicking on it.
+    pub fn add_vertex(&self) -> AddVertexTraversal<'_, S> {
         self.add_vertex_with_label(vertex::DEFAULT_LABEL)
     }
 
-    /// Spawns a traversal by adding a vertex with the specified label.
-    pub fn add_vertex_with_label<S: ToString>(&self, label: S) -> AddVertexTraversal {
-        let id = self.new_id();
+    /// Adds a vertex with the specified label, committing it immediately.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the implicit commit fails to write to storage; see
+    /// `add_vertex` for why, and use `transaction()` if you need a
+    /// `Result` instead.
+    pub fn add_vertex_with_label<L: ToString>(&self, label: L) -> AddVertexTraversal<'_, S> {
+        let transaction = self.transaction();
+        let traversal = transaction.add_vertex_with_label(label);
+        transaction
+            .commit()
+            .expect("failed to commit add_vertex_with_label");
+        traversal
+    }
 
-        let vertex = Vertex::new(id, label);
-        let mut vertices = HashMap::new();
-        vertices.insert(id, DirtyEntry::new(vertex));
+    /// Adds a vertex with the default label, registering it under its
+    /// content hash so it can be resolved by `vertex_with_id` from any
+    /// `GraphTraversalSource` over the same storage; see
+    /// `Vertex::content_hash`. Idempotent: calling this again with the
+    /// same label/properties returns the vertex already registered under
+    /// that hash instead of creating a duplicate.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the implicit commit fails to write to storage; see
+    /// `add_vertex` for why, and use `transaction()` if you need a
+    /// `Result` instead.
+    pub fn add_content_addressed_vertex(&self) -> AddVertexTraversal<'_, S> {
+        self.add_content_addressed_vertex_with_label(vertex::DEFAULT_LABEL)
+    }
 
-        AddVertexTraversal {
-            id: Some(id),
-            context: Rc::new(TraversalContext {
-                database: &self.database,
-                vertices,
-            }),
-        }
+    /// Adds a vertex with the specified label, registering it under its
+    /// content hash; see `add_content_addressed_vertex`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the implicit commit fails to write to storage; see
+    /// `add_vertex` for why, and use `transaction()` if you need a
+    /// `Result` instead.
+    pub fn add_content_addressed_vertex_with_label<L: ToString>(
+        &self,
+        label: L,
+    ) -> AddVertexTraversal<'_, S> {
+        let transaction = self.transaction();
+        let traversal = transaction.add_content_addressed_vertex_with_label(label);
+        transaction
+            .commit()
+            .expect("failed to commit add_content_addressed_vertex_with_label");
+        traversal
     }
 
     /// Spawns a traversal over all vertices.
-    pub fn vertices(&self) -> VertexTraversal {
-        let prefix_search = PrefixSearchIterator {
-            prefix_iterator: self.database.prefix_iterator(vertex::KEY_PREFIX),
-            prefix: vertex::KEY_PREFIX.as_bytes(),
-        };
+    pub fn vertices(&self) -> VertexTraversal<'_, S> {
         VertexTraversal {
-            prefix_search,
+            prefix_search: self.storage.prefix_iter(vertex::KEY_PREFIX.as_bytes()),
             label: None,
-            _context: Rc::new(TraversalContext {
-                database: &self.database,
-                vertices: HashMap::new(),
-            }),
+            context: Rc::new(RefCell::new(TraversalContext::new(&self.storage))),
         }
     }
 
     /// Spawns a traversal over the vertices with the specified label.
-    pub fn vertices_with_label<'a>(&'a self, label: &'a str) -> VertexTraversal<'a> {
-        let prefix_search = PrefixSearchIterator {
-            prefix_iterator: self.database.prefix_iterator(vertex::KEY_PREFIX),
-            prefix: vertex::KEY_PREFIX.as_bytes(),
-        };
+    pub fn vertices_with_label<'a>(&'a self, label: &'a str) -> VertexTraversal<'a, S> {
         VertexTraversal {
-            prefix_search,
+            prefix_search: self.storage.prefix_iter(vertex::KEY_PREFIX.as_bytes()),
             label: Some(label),
-            _context: Rc::new(TraversalContext {
-                database: &self.database,
-                vertices: HashMap::new(),
-            }),
+            context: Rc::new(RefCell::new(TraversalContext::new(&self.storage))),
         }
     }
 
-    /// Spawns a traversal starting with the vertex with the specified id.
-    pub fn vertex_with_id(&self, id: usize) -> VertexWithIdTraversal {
+    /// Spawns a traversal starting with the vertex with the specified id,
+    /// accepting either the internal numeric key or the base32 content
+    /// hash returned by `Vertex::content_hash`.
+    pub fn vertex_with_id<R: Into<VertexRef>>(&self, id: R) -> VertexWithIdTraversal<'_, S> {
+        let id = match id.into() {
+            VertexRef::Id(id) => Some(id),
+            VertexRef::Hash(hash) => self.resolve_hash(&hash),
+        };
+
         VertexWithIdTraversal {
-            database: &self.database,
-            id: Some(id),
-            _context: Rc::new(TraversalContext {
-                database: &self.database,
-                vertices: HashMap::new(),
-            }),
+            id,
+            context: Rc::new(RefCell::new(TraversalContext::new(&self.storage))),
+        }
+    }
+
+    /// Resolves a base32 content hash (case-insensitively) to the numeric
+    /// key it was registered under by `add_content_addressed_vertex`.
+    fn resolve_hash(&self, hash: &str) -> Option<usize> {
+        let bytes = self.storage.get(hash_key(&hash.to_ascii_uppercase()).as_bytes())?;
+        Some(bincode::deserialize(&bytes).unwrap())
+    }
+
+    /// Resolves a base32 content hash (case-insensitively) to the numeric
+    /// key it was registered under by `add_content_addressed_edge`.
+    pub(crate) fn resolve_edge_hash(&self, hash: &str) -> Option<usize> {
+        let bytes = self
+            .storage
+            .get(edge_hash_key(&hash.to_ascii_uppercase()).as_bytes())?;
+        Some(bincode::deserialize(&bytes).unwrap())
+    }
+
+    /// Adds an edge with the specified label, committing it immediately once
+    /// both `from` and `to` have been supplied and the traversal is resolved
+    /// with `next()`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the implicit commit (triggered by `next()`) fails to write
+    /// to storage; use `transaction()` and its `commit()` directly if you
+    /// need to handle a storage failure instead of panicking on it.
+    pub fn add_edge<L: ToString>(&self, label: L) -> AddEdgeTraversal<'_, S> {
+        AddEdgeTraversal::new(self, label.to_string(), None)
+    }
+
+    /// Adds an edge with the specified label, committing it immediately once
+    /// `from`/`to` are supplied, registering it under its content hash so a
+    /// second call with the same label/endpoints resolves to the edge
+    /// already created instead of creating a duplicate; see
+    /// `Edge::content_hash`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the implicit commit (triggered by `next()`) fails to write
+    /// to storage; see `add_edge` for why, and use `transaction()` if you
+    /// need a `Result` instead.
+    pub fn add_content_addressed_edge<L: ToString>(&self, label: L) -> AddEdgeTraversal<'_, S> {
+        AddEdgeTraversal::new_content_addressed(self, label.to_string(), None)
+    }
+
+    /// Spawns a traversal over all edges.
+    pub fn edges(&self) -> EdgeTraversal<'_, S> {
+        EdgeTraversal {
+            prefix_search: self.storage.prefix_iter(edge::KEY_PREFIX.as_bytes()),
+            label: None,
+            _context: Rc::new(RefCell::new(TraversalContext::new(&self.storage))),
+        }
+    }
+
+    /// Registers a secondary index on `prop`, back-filling entries for every
+    /// vertex that already has it set. From then on, vertices added through
+    /// this source keep the index up to date, and `find_by` can resolve
+    /// matches by walking the `idx_` prefix instead of scanning every
+    /// vertex.
+    pub fn create_index<K: ToString>(&self, prop: K) {
+        let prop = prop.to_string();
+        {
+            let mut indexes = self.indexes.borrow_mut();
+            if indexes.contains(&prop) {
+                return;
+            }
+            indexes.push(prop.clone());
+        }
+        self.save_indexes();
+
+        let writes: Vec<_> = self
+            .vertices()
+            .filter_map(|v| {
+                vertex::indexed_value(&v, &prop).map(|value| {
+                    (
+                        index_key(&prop, value, v.id()).into_bytes(),
+                        index_value(v.id()),
+                    )
+                })
+            })
+            .collect();
+
+        self.storage
+            .write_batch(writes)
+            .expect("failed to back-fill index");
+    }
+
+    /// Looks up vertices via a secondary index created with `create_index`.
+    /// Walks the `index_prefix(prop, value)` prefix directly instead of
+    /// scanning every vertex.
+    pub fn find_by<K: ToString, V: ToString>(&self, prop: K, value: V) -> FindByTraversal<'_, S> {
+        let prefix = index_prefix(&prop.to_string(), &value.to_string());
+        FindByTraversal {
+            prefix_search: self.storage.prefix_iter(prefix.as_bytes()),
+            context: Rc::new(RefCell::new(TraversalContext::new(&self.storage))),
         }
     }
 
-    /// Saves the context to the database.
-    fn save_context(&self) {
-        let bytes = bincode::serialize(&self.context).unwrap();
-        self.database.put(KEY_SYS_CONTEXT, bytes).unwrap();
+    /// The properties currently covered by a secondary index.
+    pub(crate) fn indexed_properties(&self) -> Vec<String> {
+        self.indexes.borrow().clone()
     }
 
-    /// Generate a new id.
+    /// The current sequence-number state as a write, to be folded into
+    /// whichever `Transaction` batch consumed the ids it advanced past; see
+    /// `new_id`.
+    pub(crate) fn context_write(&self) -> (Vec<u8>, Vec<u8>) {
+        let bytes = bincode::serialize(&self.context.get()).unwrap();
+        (KEY_SYS_CONTEXT.to_vec(), bytes)
+    }
+
+    /// Saves the registered index properties to the database.
+    fn save_indexes(&self) {
+        let bytes = bincode::serialize(&*self.indexes.borrow()).unwrap();
+        self.storage.put(KEY_SYS_INDEXES, &bytes);
+    }
+
+    /// Generate a new id. The advance is only reflected in storage once a
+    /// `Transaction` folds `context_write()` into its commit batch, so an
+    /// id consumed by a transaction that's rolled back (or never resolved)
+    /// is never observed on disk.
     fn new_id(&self) -> usize {
         let mut context = self.context.get();
         context.lsn += 1;
         self.context.set(context);
-        self.save_context();
         context.lsn
     }
 }
@@ -127,27 +309,112 @@ pub(crate) struct DirtyEntry<T> {
 }
 
 impl<T> DirtyEntry<T> {
+    /// Wraps a value that still needs to be written out.
     pub(crate) fn new(entry: T) -> DirtyEntry<T> {
         DirtyEntry { dirty: true, entry }
     }
+
+    /// Wraps a value that matches what's already persisted, e.g. one just
+    /// read back from storage.
+    pub(crate) fn clean(entry: T) -> DirtyEntry<T> {
+        DirtyEntry {
+            dirty: false,
+            entry,
+        }
+    }
 }
 
-struct TraversalContext<'a> {
-    database: &'a DBWithThreadMode<SingleThreaded>,
+/// Caches vertices/edges touched by a traversal and, for mutating
+/// traversals, accumulates the writes a `Transaction` will later commit.
+/// Dropping a context with unresolved dirty entries only warns — it no
+/// longer writes them; call `Transaction::commit` or `rollback` instead.
+pub(crate) struct TraversalContext<'a, S: Storage> {
+    storage: &'a S,
     vertices: HashMap<usize, DirtyEntry<Vertex>>,
+    edges: HashMap<usize, DirtyEntry<Edge>>,
+    adjacency: HashMap<String, DirtyEntry<usize>>,
+    hashes: HashMap<String, DirtyEntry<usize>>,
+    edge_hashes: HashMap<String, DirtyEntry<usize>>,
+    resolved: bool,
 }
 
-impl<'a> Drop for TraversalContext<'a> {
-    fn drop(&mut self) {
-        eprintln!("dropping traversal context");
-        dbg!(&self.vertices);
+impl<'a, S: Storage> TraversalContext<'a, S> {
+    fn new(storage: &'a S) -> TraversalContext<'a, S> {
+        TraversalContext {
+            storage,
+            vertices: HashMap::new(),
+            edges: HashMap::new(),
+            adjacency: HashMap::new(),
+            hashes: HashMap::new(),
+            edge_hashes: HashMap::new(),
+            resolved: false,
+        }
+    }
+
+    /// Collects every dirty entry into the writes a `Transaction::commit`
+    /// should apply atomically.
+    fn pending_writes(&self) -> Vec<(Vec<u8>, Vec<u8>)> {
+        let mut writes = Vec::new();
         for (id, vertex) in &self.vertices {
             if vertex.dirty {
-                let key = create_vertex_key(*id);
+                let key = create_vertex_key(*id).into_bytes();
                 let value = bincode::serialize(&vertex.entry).unwrap();
-                self.database.put(key, value).unwrap();
+                writes.push((key, value));
+            }
+        }
+        for (id, edge) in &self.edges {
+            if edge.dirty {
+                let key = create_edge_key(*id).into_bytes();
+                let value = bincode::serialize(&edge.entry).unwrap();
+                writes.push((key, value));
+            }
+        }
+        for (key, entry) in &self.adjacency {
+            if entry.dirty {
+                let value = bincode::serialize(&entry.entry).unwrap();
+                writes.push((key.clone().into_bytes(), value));
             }
         }
+        for (hash, entry) in &self.hashes {
+            if entry.dirty {
+                let key = hash_key(hash).into_bytes();
+                let value = bincode::serialize(&entry.entry).unwrap();
+                writes.push((key, value));
+            }
+        }
+        for (hash, entry) in &self.edge_hashes {
+            if entry.dirty {
+                let key = edge_hash_key(hash).into_bytes();
+                let value = bincode::serialize(&entry.entry).unwrap();
+                writes.push((key, value));
+            }
+        }
+        writes
+    }
+
+    /// Marks this context as resolved (committed or rolled back) so `Drop`
+    /// doesn't warn about it.
+    fn resolve(&mut self) {
+        self.resolved = true;
+    }
+
+    fn has_dirty_entries(&self) -> bool {
+        self.vertices.values().any(|v| v.dirty)
+            || self.edges.values().any(|e| e.dirty)
+            || self.adjacency.values().any(|a| a.dirty)
+            || self.hashes.values().any(|h| h.dirty)
+            || self.edge_hashes.values().any(|h| h.dirty)
+    }
+}
+
+impl<'a, S: Storage> Drop for TraversalContext<'a, S> {
+    fn drop(&mut self) {
+        if !self.resolved && self.has_dirty_entries() {
+            eprintln!(
+                "warning: traversal context dropped with uncommitted changes; \
+                 they were discarded. Use Transaction::commit to persist them."
+            );
+        }
     }
 }
 
@@ -155,6 +422,120 @@ fn create_vertex_key(id: usize) -> String {
     format!("{}{}", vertex::KEY_PREFIX, id)
 }
 
+fn create_edge_key(id: usize) -> String {
+    format!("{}{}", edge::KEY_PREFIX, id)
+}
+
+fn adj_out_key(vertex_id: usize, edge_id: usize) -> String {
+    format!("adj_out_{}_{}", vertex_id, edge_id)
+}
+
+fn adj_in_key(vertex_id: usize, edge_id: usize) -> String {
+    format!("adj_in_{}_{}", vertex_id, edge_id)
+}
+
+pub(crate) fn index_key(prop: &str, value: &str, vertex_id: usize) -> String {
+    format!("{}{}", index_prefix(prop, value), vertex_id)
+}
+
+/// The prefix shared by every index entry for `prop`/`value`. Each segment
+/// is preceded by its own byte length so that, e.g., `prop="label"`,
+/// `value="person"` can never prefix-match an entry for
+/// `value="person_x"` — the two encode different lengths before the `:`
+/// delimiter, so they diverge before the value even starts.
+pub(crate) fn index_prefix(prop: &str, value: &str) -> String {
+    format!("idx_{}:{}_{}:{}_", prop.len(), prop, value.len(), value)
+}
+
+pub(crate) fn index_value(vertex_id: usize) -> Vec<u8> {
+    bincode::serialize(&vertex_id).unwrap()
+}
+
+/// The properties currently covered by a secondary index, read directly
+/// from storage. Query planning (`VertexTraversal::has_label`/`has`) only
+/// has a `TraversalContext`'s storage handle to work with, not the
+/// `GraphTraversalSource` that created it, so it consults storage
+/// directly instead of going through `GraphTraversalSource::indexed_properties`;
+/// `create_index` always persists before returning, so this is never stale.
+pub(crate) fn indexed_properties_in_storage<S: Storage>(storage: &S) -> Vec<String> {
+    match storage.get(KEY_SYS_INDEXES) {
+        None => Vec::new(),
+        Some(x) => bincode::deserialize(&x).unwrap(),
+    }
+}
+
+pub(crate) fn hash_key(hash: &str) -> String {
+    format!("hsh_{}", hash)
+}
+
+pub(crate) fn edge_hash_key(hash: &str) -> String {
+    format!("ehsh_{}", hash)
+}
+
+/// A vertex reference accepted by `GraphTraversalSource::vertex_with_id`:
+/// either the internal numeric key, or the base32 content hash registered
+/// by `add_content_addressed_vertex`.
+pub enum VertexRef {
+    Id(usize),
+    Hash(String),
+}
+
+impl From<usize> for VertexRef {
+    fn from(id: usize) -> Self {
+        VertexRef::Id(id)
+    }
+}
+
+impl From<&str> for VertexRef {
+    fn from(hash: &str) -> Self {
+        VertexRef::Hash(hash.to_string())
+    }
+}
+
+impl From<String> for VertexRef {
+    fn from(hash: String) -> Self {
+        VertexRef::Hash(hash)
+    }
+}
+
+/// Resolves a vertex by id, preferring whatever this traversal's context
+/// already has cached (including not-yet-committed writes) before falling
+/// back to storage.
+pub(crate) fn fetch_vertex<'a, S: Storage>(
+    context: &Rc<RefCell<TraversalContext<'a, S>>>,
+    id: usize,
+) -> Option<Vertex> {
+    let mut context = context.borrow_mut();
+    if let Some(entry) = context.vertices.get(&id) {
+        return Some(entry.entry.clone());
+    }
+
+    let key = create_vertex_key(id);
+    let bytes = context.storage.get(key.as_bytes())?;
+    let vertex: Vertex = bincode::deserialize(&bytes).unwrap();
+    context.vertices.insert(id, DirtyEntry::clean(vertex.clone()));
+    Some(vertex)
+}
+
+/// Resolves an edge by id, preferring whatever this traversal's context
+/// already has cached (including not-yet-committed writes) before falling
+/// back to storage; the edge counterpart to `fetch_vertex`.
+pub(crate) fn fetch_edge<'a, S: Storage>(
+    context: &Rc<RefCell<TraversalContext<'a, S>>>,
+    id: usize,
+) -> Option<Edge> {
+    let mut context = context.borrow_mut();
+    if let Some(entry) = context.edges.get(&id) {
+        return Some(entry.entry.clone());
+    }
+
+    let key = create_edge_key(id);
+    let bytes = context.storage.get(key.as_bytes())?;
+    let edge: Edge = bincode::deserialize(&bytes).unwrap();
+    context.edges.insert(id, DirtyEntry::clean(edge.clone()));
+    Some(edge)
+}
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 struct GraphContext {
     /// Last sequence number
@@ -265,4 +646,471 @@ mod tests {
         let actual2: Vec<_> = graph.vertex_with_id(v2.id()).collect();
         assert_eq!(actual2, vec![v2]);
     }
+
+    #[test]
+    fn vertex_with_id_returns_none_for_an_unknown_numeric_id() {
+        let graph = GraphTraversalSource::new_in_memory();
+        let actual: Vec<_> = graph.vertex_with_id(999_usize).collect();
+        assert_eq!(actual, Vec::new());
+    }
+
+    #[test]
+    fn content_addressed_vertices_with_same_label_are_deduplicated() {
+        let graph = GraphTraversalSource::new_in_memory();
+
+        let v1 = graph
+            .add_content_addressed_vertex_with_label("person")
+            .next()
+            .unwrap();
+        let v2 = graph
+            .add_content_addressed_vertex_with_label("person")
+            .next()
+            .unwrap();
+
+        // Same label/properties hash to the same identity, so the second
+        // call resolves to the first vertex instead of creating (and
+        // orphaning) a duplicate.
+        assert_eq!(v1.id(), v2.id());
+        assert_eq!(v1.content_hash(), v2.content_hash());
+        assert_eq!(graph.vertices().count(), 1);
+    }
+
+    #[test]
+    fn content_addressed_vertices_are_deduplicated_within_one_transaction() {
+        let graph = GraphTraversalSource::new_in_memory();
+
+        let transaction = graph.transaction();
+        let v1 = transaction
+            .add_content_addressed_vertex_with_label("person")
+            .next()
+            .unwrap();
+        let v2 = transaction
+            .add_content_addressed_vertex_with_label("person")
+            .next()
+            .unwrap();
+        transaction.commit().unwrap();
+
+        assert_eq!(v1.id(), v2.id());
+        assert_eq!(graph.vertices().count(), 1);
+    }
+
+    #[test]
+    fn vertex_with_id_resolves_by_content_hash() {
+        let graph = GraphTraversalSource::new_in_memory();
+
+        let v1 = graph
+            .add_content_addressed_vertex_with_label("person")
+            .next()
+            .unwrap();
+        let hash = v1.content_hash();
+
+        let actual: Vec<_> = graph.vertex_with_id(hash.as_str()).collect();
+        assert_eq!(actual, vec![v1.clone()]);
+
+        let actual_lowercase: Vec<_> = graph.vertex_with_id(hash.to_ascii_lowercase()).collect();
+        assert_eq!(actual_lowercase, vec![v1]);
+    }
+
+    #[test]
+    fn vertex_with_id_rejects_unknown_hash() {
+        let graph = GraphTraversalSource::new_in_memory();
+        let actual: Vec<_> = graph.vertex_with_id("NOTAREALHASH1").collect();
+        assert_eq!(actual, Vec::new());
+    }
+
+    #[test]
+    fn add_edge_between_vertices() {
+        let config = TestContext::generate();
+        let graph = GraphTraversalSource::new(&config.filepath);
+
+        let v1 = graph.add_vertex().next().unwrap();
+        let v2 = graph.add_vertex().next().unwrap();
+
+        let edge = graph
+            .add_edge("knows")
+            .from(v1.id())
+            .to(v2.id())
+            .next()
+            .unwrap();
+
+        assert_eq!(edge.out_v(), v1.id());
+        assert_eq!(edge.in_v(), v2.id());
+        assert_eq!(edge.label, "knows");
+    }
+
+    #[test]
+    fn content_addressed_edges_with_same_endpoints_are_deduplicated() {
+        let graph = GraphTraversalSource::new_in_memory();
+
+        let v1 = graph.add_vertex().next().unwrap();
+        let v2 = graph.add_vertex().next().unwrap();
+
+        let e1 = graph
+            .add_content_addressed_edge("knows")
+            .from(v1.id())
+            .to(v2.id())
+            .next()
+            .unwrap();
+        let e2 = graph
+            .add_content_addressed_edge("knows")
+            .from(v1.id())
+            .to(v2.id())
+            .next()
+            .unwrap();
+
+        // Same label/endpoints hash to the same identity, so the second
+        // call resolves to the first edge instead of creating (and
+        // orphaning) a duplicate.
+        assert_eq!(e1.id(), e2.id());
+        assert_eq!(e1.content_hash(), e2.content_hash());
+        assert_eq!(graph.edges().count(), 1);
+    }
+
+    #[test]
+    fn traverse_out_edges() {
+        let config = TestContext::generate();
+        let graph = GraphTraversalSource::new(&config.filepath);
+
+        let v1 = graph.add_vertex().next().unwrap();
+        let v2 = graph.add_vertex().next().unwrap();
+        let v3 = graph.add_vertex().next().unwrap();
+
+        graph.add_edge("knows").from(v1.id()).to(v2.id()).next();
+        graph.add_edge("knows").from(v1.id()).to(v3.id()).next();
+
+        let actual: HashMap<_, _> = graph
+            .vertex_with_id(v1.id())
+            .out("knows")
+            .map(|v| (v.id(), v))
+            .collect();
+
+        let mut expected = HashMap::new();
+        expected.insert(v2.id(), v2);
+        expected.insert(v3.id(), v3);
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn traverse_in_edges() {
+        let config = TestContext::generate();
+        let graph = GraphTraversalSource::new(&config.filepath);
+
+        let v1 = graph.add_vertex().next().unwrap();
+        let v2 = graph.add_vertex().next().unwrap();
+        let v3 = graph.add_vertex().next().unwrap();
+
+        graph.add_edge("knows").from(v1.id()).to(v2.id()).next();
+        graph.add_edge("knows").from(v3.id()).to(v2.id()).next();
+
+        let actual: HashMap<_, _> = graph
+            .vertex_with_id(v2.id())
+            .r#in("knows")
+            .map(|v| (v.id(), v))
+            .collect();
+
+        let mut expected = HashMap::new();
+        expected.insert(v1.id(), v1);
+        expected.insert(v3.id(), v3);
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn traverse_both_edges() {
+        let config = TestContext::generate();
+        let graph = GraphTraversalSource::new(&config.filepath);
+
+        let v1 = graph.add_vertex().next().unwrap();
+        let v2 = graph.add_vertex().next().unwrap();
+        let v3 = graph.add_vertex().next().unwrap();
+
+        // v1 -> v2 is outgoing from v1's perspective, v3 -> v1 is incoming;
+        // `both` should pick up the other endpoint from each direction.
+        graph.add_edge("knows").from(v1.id()).to(v2.id()).next();
+        graph.add_edge("knows").from(v3.id()).to(v1.id()).next();
+
+        let actual: HashMap<_, _> = graph
+            .vertex_with_id(v1.id())
+            .both("knows")
+            .map(|v| (v.id(), v))
+            .collect();
+
+        let mut expected = HashMap::new();
+        expected.insert(v2.id(), v2);
+        expected.insert(v3.id(), v3);
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn in_memory_graph_round_trips_vertices_and_edges() {
+        let graph = GraphTraversalSource::new_in_memory();
+
+        let v1 = graph.add_vertex().next().unwrap();
+        let v2 = graph.add_vertex_with_label("custom").next().unwrap();
+        graph.add_edge("knows").from(v1.id()).to(v2.id()).next();
+
+        let actual: HashMap<_, _> = graph.vertices().map(|v| (v.id(), v)).collect();
+        let mut expected = HashMap::new();
+        expected.insert(v1.id(), v1.clone());
+        expected.insert(v2.id(), v2.clone());
+        assert_eq!(actual, expected);
+
+        let adjacent: Vec<_> = graph.vertex_with_id(v1.id()).out("knows").collect();
+        assert_eq!(adjacent, vec![v2]);
+    }
+
+    #[test]
+    fn transaction_commit_persists_batched_mutations() {
+        let graph = GraphTraversalSource::new_in_memory();
+
+        let transaction = graph.transaction();
+        let v1 = transaction.add_vertex().next().unwrap();
+        let v2 = transaction.add_vertex().next().unwrap();
+        transaction
+            .add_edge("knows")
+            .from(v1.id())
+            .to(v2.id())
+            .next();
+        transaction.commit().unwrap();
+
+        let actual: HashMap<_, _> = graph.vertices().map(|v| (v.id(), v)).collect();
+        let mut expected = HashMap::new();
+        expected.insert(v1.id(), v1.clone());
+        expected.insert(v2.id(), v2);
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn transaction_rollback_discards_mutations() {
+        let graph = GraphTraversalSource::new_in_memory();
+
+        let transaction = graph.transaction();
+        transaction.add_vertex().next();
+        transaction.rollback();
+
+        assert_eq!(graph.vertices().count(), 0);
+    }
+
+    #[test]
+    fn transaction_rollback_does_not_persist_the_id_advance() {
+        let config = TestContext::generate();
+
+        {
+            let graph = GraphTraversalSource::new(&config.filepath);
+            let transaction = graph.transaction();
+            transaction.add_vertex().next();
+            transaction.rollback();
+        }
+
+        // The id consumed by the rolled-back transaction was never folded
+        // into a committed batch, so it was never persisted; reopening the
+        // same storage should hand it out again rather than skipping it.
+        let graph = GraphTraversalSource::new(&config.filepath);
+        let v1 = graph.add_vertex().next().unwrap();
+        assert_eq!(v1.id(), 1);
+    }
+
+    #[test]
+    fn query_has_label_filters_vertices() {
+        let graph = GraphTraversalSource::new_in_memory();
+
+        let v1 = graph.add_vertex_with_label("person").next().unwrap();
+        graph.add_vertex_with_label("place").next().unwrap();
+        let v2 = graph.add_vertex_with_label("person").next().unwrap();
+
+        let actual: HashMap<_, _> = graph
+            .vertices()
+            .has_label("person")
+            .map(|v| (v.id(), v))
+            .collect();
+
+        let mut expected = HashMap::new();
+        expected.insert(v1.id(), v1);
+        expected.insert(v2.id(), v2);
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn query_has_filters_on_property() {
+        let graph = GraphTraversalSource::new_in_memory();
+        graph.add_vertex().next();
+        graph.add_vertex().next();
+
+        // No vertex has a "name" property yet, so the step should drop
+        // everything rather than matching by accident.
+        let actual: Vec<_> = graph.vertices().has("name", "alice").collect();
+        assert_eq!(actual, Vec::new());
+    }
+
+    #[test]
+    fn query_has_matches_a_vertex_with_the_property_set() {
+        let graph = GraphTraversalSource::new_in_memory();
+
+        let transaction = graph.transaction();
+        let v1 = transaction
+            .add_vertex()
+            .property("name", "alice")
+            .next()
+            .unwrap();
+        transaction.add_vertex().next();
+        transaction.commit().unwrap();
+
+        let actual: Vec<_> = graph.vertices().has("name", "alice").collect();
+        assert_eq!(actual, vec![v1]);
+    }
+
+    #[test]
+    fn query_has_label_walks_the_index_when_one_exists() {
+        let graph = GraphTraversalSource::new_in_memory();
+
+        let v1 = graph.add_vertex_with_label("person").next().unwrap();
+        graph.add_vertex_with_label("place").next().unwrap();
+        let v2 = graph.add_vertex_with_label("person").next().unwrap();
+
+        graph.create_index("label");
+
+        // has_label should plan over the index created above rather than
+        // the full vtx_ scan, but the result is the same either way.
+        let actual: HashMap<_, _> = graph
+            .vertices()
+            .has_label("person")
+            .map(|v| (v.id(), v))
+            .collect();
+
+        let mut expected = HashMap::new();
+        expected.insert(v1.id(), v1);
+        expected.insert(v2.id(), v2);
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn query_has_walks_the_index_when_one_exists() {
+        let graph = GraphTraversalSource::new_in_memory();
+
+        let transaction = graph.transaction();
+        let v1 = transaction
+            .add_vertex()
+            .property("name", "alice")
+            .next()
+            .unwrap();
+        transaction
+            .add_vertex()
+            .property("name", "bob")
+            .next()
+            .unwrap();
+        transaction.commit().unwrap();
+
+        graph.create_index("name");
+
+        let actual: Vec<_> = graph.vertices().has("name", "alice").collect();
+        assert_eq!(actual, vec![v1]);
+    }
+
+    #[test]
+    fn query_has_label_ignores_the_index_when_already_narrowed_by_label() {
+        let graph = GraphTraversalSource::new_in_memory();
+
+        let v1 = graph.add_vertex_with_label("person").next().unwrap();
+        graph.add_vertex_with_label("place").next().unwrap();
+
+        graph.create_index("label");
+
+        // vertices_with_label has already narrowed the scan to "person";
+        // has_label("place") must still see no matches rather than
+        // bypassing that narrowing via the index.
+        let actual: Vec<_> = graph
+            .vertices_with_label("person")
+            .has_label("place")
+            .collect();
+        assert_eq!(actual, Vec::new());
+
+        let actual: Vec<_> = graph
+            .vertices_with_label("person")
+            .has_label("person")
+            .collect();
+        assert_eq!(actual, vec![v1]);
+    }
+
+    #[test]
+    fn find_by_backfills_existing_vertices() {
+        let graph = GraphTraversalSource::new_in_memory();
+
+        let v1 = graph.add_vertex_with_label("person").next().unwrap();
+        graph.add_vertex_with_label("place").next().unwrap();
+        let v2 = graph.add_vertex_with_label("person").next().unwrap();
+
+        graph.create_index("label");
+
+        let actual: HashMap<_, _> = graph
+            .find_by("label", "person")
+            .map(|v| (v.id(), v))
+            .collect();
+        let mut expected = HashMap::new();
+        expected.insert(v1.id(), v1);
+        expected.insert(v2.id(), v2);
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn find_by_does_not_match_a_value_with_the_prefix_as_a_substring() {
+        let graph = GraphTraversalSource::new_in_memory();
+
+        let v1 = graph.add_vertex_with_label("person").next().unwrap();
+        graph.add_vertex_with_label("person_x").next().unwrap();
+
+        graph.create_index("label");
+
+        let actual: Vec<_> = graph.find_by("label", "person").collect();
+        assert_eq!(actual, vec![v1]);
+    }
+
+    #[test]
+    fn find_by_sees_vertices_added_after_the_index_was_created() {
+        let graph = GraphTraversalSource::new_in_memory();
+
+        graph.create_index("label");
+        let v1 = graph.add_vertex_with_label("person").next().unwrap();
+
+        let actual: Vec<_> = graph.find_by("label", "person").collect();
+        assert_eq!(actual, vec![v1]);
+    }
+
+    #[test]
+    fn query_limit_caps_results() {
+        let graph = GraphTraversalSource::new_in_memory();
+        for _ in 0..5 {
+            graph.add_vertex().next();
+        }
+
+        let actual: Vec<_> = graph.vertices().limit(2).collect();
+        assert_eq!(actual.len(), 2);
+    }
+
+    #[test]
+    fn query_limit_then_filter_does_not_reorder_across_limit() {
+        let graph = GraphTraversalSource::new_in_memory();
+        graph.add_vertex_with_label("place").next();
+        graph.add_vertex_with_label("place").next();
+        graph.add_vertex_with_label("person").next();
+
+        // `limit` must cut the stream to the first two vertices before
+        // `has_label` filters it, not after — even though `has_label` is
+        // the more selective step, it must not reach past the cutoff to
+        // the "person" vertex.
+        let actual: Vec<_> = graph.vertices().limit(2).has_label("person").collect();
+        assert_eq!(actual, Vec::new());
+    }
+
+    #[test]
+    fn query_limit_then_dedup_does_not_reorder_across_limit() {
+        let graph = GraphTraversalSource::new_in_memory();
+        for _ in 0..5 {
+            graph.add_vertex().next();
+        }
+
+        let actual: Vec<_> = graph.vertices().limit(2).dedup().collect();
+        assert_eq!(actual.len(), 2);
+    }
 }
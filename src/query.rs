@@ -0,0 +1,144 @@
+use crate::vertex::Vertex;
+use std::collections::HashSet;
+use std::rc::Rc;
+
+/// A single lazy filtering/limiting operation in a query pipeline. Built up
+/// by the builder methods on `VertexTraversal`/`VertexQuery` and only turned
+/// into an actual iterator chain once the pipeline is compiled.
+pub(crate) enum Step<'a> {
+    HasLabel(String),
+    Has(String, String),
+    Where(Rc<dyn Fn(&Vertex) -> bool + 'a>),
+    Dedup,
+    Limit(usize),
+}
+
+impl<'a> Step<'a> {
+    /// Lower ranks run first within a run of consecutive filter steps (see
+    /// `VertexQuery::reorder_filters`). Only meaningful for
+    /// `HasLabel`/`Has`/`Where`; `Dedup`/`Limit` never take part in this
+    /// ordering.
+    fn rank(&self) -> u8 {
+        match self {
+            Step::HasLabel(_) => 0,
+            Step::Has(_, _) | Step::Where(_) => 1,
+            Step::Dedup => 2,
+            Step::Limit(_) => 3,
+        }
+    }
+}
+
+/// A composable, lazily-executed chain of [`Step`]s over a vertex traversal.
+/// Steps accumulate as the caller chains builder methods. Consecutive
+/// filter steps (`has_label`/`has`/`where`) are reordered most-selective
+/// first the moment the query is first polled, since filters commute with
+/// each other; `dedup`/`limit` are never moved relative to other steps,
+/// since reordering across either would change which vertices they see.
+/// This only reorders filters against each other once they're already
+/// steps in the pipeline — the index-aware part of planning happens one
+/// level up, in `VertexTraversal::has_label`/`has`, which swap in a
+/// secondary-index-backed source instead of a full `vtx_` scan when one of
+/// those is the first step over an unfiltered traversal; see
+/// `VertexTraversal::indexed_source`.
+pub struct VertexQuery<'a> {
+    source: Option<Box<dyn Iterator<Item = Vertex> + 'a>>,
+    steps: Vec<Step<'a>>,
+    compiled: Option<Box<dyn Iterator<Item = Vertex> + 'a>>,
+}
+
+impl<'a> VertexQuery<'a> {
+    pub(crate) fn new(source: Box<dyn Iterator<Item = Vertex> + 'a>) -> VertexQuery<'a> {
+        VertexQuery {
+            source: Some(source),
+            steps: Vec::new(),
+            compiled: None,
+        }
+    }
+
+    pub(crate) fn push(mut self, step: Step<'a>) -> Self {
+        self.steps.push(step);
+        self
+    }
+
+    /// Keeps only vertices with the given label.
+    pub fn has_label<L: ToString>(self, label: L) -> Self {
+        self.push(Step::HasLabel(label.to_string()))
+    }
+
+    /// Keeps only vertices with a matching `key`/`value` property.
+    pub fn has<K: ToString, V: ToString>(self, key: K, value: V) -> Self {
+        self.push(Step::Has(key.to_string(), value.to_string()))
+    }
+
+    /// Keeps only vertices matching an arbitrary predicate.
+    pub fn r#where<F: Fn(&Vertex) -> bool + 'a>(self, predicate: F) -> Self {
+        self.push(Step::Where(Rc::new(predicate)))
+    }
+
+    /// Drops vertices already seen earlier in the pipeline.
+    pub fn dedup(self) -> Self {
+        self.push(Step::Dedup)
+    }
+
+    /// Stops the traversal after `n` vertices.
+    pub fn limit(self, n: usize) -> Self {
+        self.push(Step::Limit(n))
+    }
+
+    fn compile(&mut self) -> &mut Box<dyn Iterator<Item = Vertex> + 'a> {
+        if self.compiled.is_none() {
+            let steps = std::mem::take(&mut self.steps);
+            let steps = Self::reorder_filters(steps);
+
+            let mut iter = self.source.take().expect("query already compiled");
+            for step in steps {
+                iter = match step {
+                    Step::HasLabel(label) => Box::new(iter.filter(move |v| v.label == label)),
+                    Step::Has(key, value) => {
+                        Box::new(iter.filter(move |v| v.prop.get(&key) == Some(&value)))
+                    }
+                    Step::Where(predicate) => Box::new(iter.filter(move |v| predicate(v))),
+                    Step::Dedup => {
+                        let mut seen = HashSet::new();
+                        Box::new(iter.filter(move |v| seen.insert(v.id())))
+                    }
+                    Step::Limit(n) => Box::new(iter.take(n)),
+                };
+            }
+            self.compiled = Some(iter);
+        }
+
+        self.compiled.as_mut().unwrap()
+    }
+
+    /// Sorts each run of consecutive filter steps (`HasLabel`/`Has`/`Where`)
+    /// most-selective first, without moving any step across a
+    /// `Dedup`/`Limit` barrier. Filters commute with each other, so
+    /// reordering within a run doesn't change the result; `Dedup`/`Limit`
+    /// are order-sensitive, so a filter can never jump past one.
+    fn reorder_filters(steps: Vec<Step<'a>>) -> Vec<Step<'a>> {
+        let mut ordered = Vec::with_capacity(steps.len());
+        let mut run = Vec::new();
+        for step in steps {
+            match step {
+                Step::Dedup | Step::Limit(_) => {
+                    run.sort_by_key(Step::rank);
+                    ordered.append(&mut run);
+                    ordered.push(step);
+                }
+                filter => run.push(filter),
+            }
+        }
+        run.sort_by_key(Step::rank);
+        ordered.append(&mut run);
+        ordered
+    }
+}
+
+impl<'a> Iterator for VertexQuery<'a> {
+    type Item = Vertex;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.compile().next()
+    }
+}
@@ -0,0 +1,176 @@
+use rocksdb::{DBIteratorWithThreadMode, DBWithThreadMode, SingleThreaded, WriteBatch, DB};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::path::Path;
+
+/// An error from the underlying storage backend, e.g. a failed RocksDB
+/// write.
+#[derive(Debug)]
+pub struct StorageError {
+    message: String,
+}
+
+impl StorageError {
+    fn new<S: Into<String>>(message: S) -> StorageError {
+        StorageError {
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl Error for StorageError {}
+
+/// A key-value backend for a `GraphTraversalSource`. Implementors only need
+/// to support point reads/writes, ordered prefix scans, and atomic batched
+/// writes; everything else in the crate is built on top of those
+/// primitives.
+pub trait Storage {
+    type Iter<'a>: Iterator<Item = (Box<[u8]>, Box<[u8]>)>
+    where
+        Self: 'a;
+
+    fn get(&self, key: &[u8]) -> Option<Vec<u8>>;
+
+    fn put(&self, key: &[u8], value: &[u8]);
+
+    fn delete(&self, key: &[u8]);
+
+    /// Iterates over all entries whose key starts with `prefix`, in key
+    /// order.
+    fn prefix_iter<'a>(&'a self, prefix: &[u8]) -> Self::Iter<'a>;
+
+    /// Applies every write in a single atomic batch: either all of them
+    /// land, or none do.
+    fn write_batch(&self, writes: Vec<(Vec<u8>, Vec<u8>)>) -> Result<(), StorageError>;
+}
+
+/// The on-disk backend, backed by RocksDB.
+pub struct RocksStorage {
+    db: DBWithThreadMode<SingleThreaded>,
+}
+
+impl RocksStorage {
+    pub fn open<P: AsRef<Path>>(path: P) -> RocksStorage {
+        RocksStorage {
+            db: DB::open_default(path).unwrap(),
+        }
+    }
+}
+
+pub struct RocksPrefixIter<'a> {
+    iterator: DBIteratorWithThreadMode<'a, DBWithThreadMode<SingleThreaded>>,
+    prefix: Vec<u8>,
+}
+
+impl<'a> Iterator for RocksPrefixIter<'a> {
+    type Item = (Box<[u8]>, Box<[u8]>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (key, value) = self.iterator.next()?.unwrap();
+
+        // ensure we're still in the right prefix
+        if !key.starts_with(&self.prefix) {
+            return None;
+        }
+
+        Some((key, value))
+    }
+}
+
+impl Storage for RocksStorage {
+    type Iter<'a> = RocksPrefixIter<'a>;
+
+    fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        self.db.get(key).unwrap()
+    }
+
+    fn put(&self, key: &[u8], value: &[u8]) {
+        self.db.put(key, value).unwrap();
+    }
+
+    fn delete(&self, key: &[u8]) {
+        self.db.delete(key).unwrap();
+    }
+
+    fn prefix_iter<'a>(&'a self, prefix: &[u8]) -> Self::Iter<'a> {
+        RocksPrefixIter {
+            iterator: self.db.prefix_iterator(prefix),
+            prefix: prefix.to_vec(),
+        }
+    }
+
+    fn write_batch(&self, writes: Vec<(Vec<u8>, Vec<u8>)>) -> Result<(), StorageError> {
+        let mut batch = WriteBatch::default();
+        for (key, value) in writes {
+            batch.put(key, value);
+        }
+        self.db
+            .write(batch)
+            .map_err(|err| StorageError::new(err.to_string()))
+    }
+}
+
+/// An in-memory backend, useful for tests and embedders that don't want to
+/// touch disk. Not persisted across process restarts.
+#[derive(Default)]
+pub struct MemoryStorage {
+    entries: RefCell<BTreeMap<Vec<u8>, Vec<u8>>>,
+}
+
+impl MemoryStorage {
+    pub fn new() -> MemoryStorage {
+        MemoryStorage {
+            entries: RefCell::new(BTreeMap::new()),
+        }
+    }
+}
+
+impl Storage for MemoryStorage {
+    type Iter<'a> = std::vec::IntoIter<(Box<[u8]>, Box<[u8]>)>;
+
+    fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        self.entries.borrow().get(key).cloned()
+    }
+
+    fn put(&self, key: &[u8], value: &[u8]) {
+        self.entries
+            .borrow_mut()
+            .insert(key.to_vec(), value.to_vec());
+    }
+
+    fn delete(&self, key: &[u8]) {
+        self.entries.borrow_mut().remove(key);
+    }
+
+    fn prefix_iter<'a>(&'a self, prefix: &[u8]) -> Self::Iter<'a> {
+        let matches: Vec<_> = self
+            .entries
+            .borrow()
+            .range(prefix.to_vec()..)
+            .take_while(|(key, _)| key.starts_with(prefix))
+            .map(|(key, value)| {
+                (
+                    key.clone().into_boxed_slice(),
+                    value.clone().into_boxed_slice(),
+                )
+            })
+            .collect();
+        matches.into_iter()
+    }
+
+    fn write_batch(&self, writes: Vec<(Vec<u8>, Vec<u8>)>) -> Result<(), StorageError> {
+        let mut entries = self.entries.borrow_mut();
+        for (key, value) in writes {
+            entries.insert(key, value);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,152 @@
+use crate::edge::AddEdgeTraversal;
+use crate::error::GraphError;
+use crate::storage::Storage;
+use crate::vertex::{AddVertexTraversal, Vertex};
+use crate::{base32, vertex, DirtyEntry, GraphTraversalSource, TraversalContext};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// An explicit, atomic unit of mutation. Vertices and edges added through a
+/// `Transaction` are only visible to other connections once `commit()`
+/// succeeds; `rollback()` discards them instead.
+pub struct Transaction<'a, S: Storage> {
+    pub(crate) source: &'a GraphTraversalSource<S>,
+    pub(crate) context: Rc<RefCell<TraversalContext<'a, S>>>,
+}
+
+impl<'a, S: Storage> Transaction<'a, S> {
+    /// Adds a vertex with the default label to this transaction.
+    pub fn add_vertex(&self) -> AddVertexTraversal<'a, S> {
+        self.add_vertex_with_label(vertex::DEFAULT_LABEL)
+    }
+
+    /// Adds a vertex with the specified label to this transaction.
+    pub fn add_vertex_with_label<L: ToString>(&self, label: L) -> AddVertexTraversal<'a, S> {
+        let id = self.source.new_id();
+        let vertex = Vertex::new(id, label);
+        self.context
+            .borrow_mut()
+            .vertices
+            .insert(id, DirtyEntry::new(vertex));
+
+        AddVertexTraversal {
+            id: Some(id),
+            context: Rc::clone(&self.context),
+        }
+    }
+
+    /// Adds a vertex with the default label to this transaction, registered
+    /// under its content hash; see
+    /// `GraphTraversalSource::add_content_addressed_vertex`.
+    pub fn add_content_addressed_vertex(&self) -> AddVertexTraversal<'a, S> {
+        self.add_content_addressed_vertex_with_label(vertex::DEFAULT_LABEL)
+    }
+
+    /// Adds a vertex with the specified label to this transaction,
+    /// registered under its content hash. Content addressing is
+    /// idempotent: a second call with the same label/properties resolves
+    /// to the vertex already registered under that hash instead of
+    /// creating (and orphaning) a duplicate.
+    pub fn add_content_addressed_vertex_with_label<L: ToString>(
+        &self,
+        label: L,
+    ) -> AddVertexTraversal<'a, S> {
+        let label = label.to_string();
+        let hash = base32::encode_id(vertex::content_address(&label, &HashMap::new()));
+
+        if let Some(id) = self.lookup_hash(&hash) {
+            if crate::fetch_vertex(&self.context, id).is_some() {
+                return AddVertexTraversal {
+                    id: Some(id),
+                    context: Rc::clone(&self.context),
+                };
+            }
+        }
+
+        let id = self.source.new_id();
+        let vertex = Vertex::new(id, label);
+
+        let mut context = self.context.borrow_mut();
+        context.hashes.insert(hash, DirtyEntry::new(id));
+        context.vertices.insert(id, DirtyEntry::new(vertex));
+        drop(context);
+
+        AddVertexTraversal {
+            id: Some(id),
+            context: Rc::clone(&self.context),
+        }
+    }
+
+    /// Resolves `hash` to a vertex id, preferring a mapping registered
+    /// earlier in this transaction before falling back to one already
+    /// committed to storage.
+    fn lookup_hash(&self, hash: &str) -> Option<usize> {
+        if let Some(entry) = self.context.borrow().hashes.get(hash) {
+            return Some(entry.entry);
+        }
+        self.source.resolve_hash(hash)
+    }
+
+    /// Adds an edge with the specified label to this transaction, once
+    /// `from`/`to` are supplied on the returned builder.
+    pub fn add_edge<L: ToString>(&self, label: L) -> AddEdgeTraversal<'a, S> {
+        AddEdgeTraversal::new(self.source, label.to_string(), Some(Rc::clone(&self.context)))
+    }
+
+    /// Adds an edge with the specified label to this transaction, registered
+    /// under its content hash once `from`/`to` are supplied; see
+    /// `GraphTraversalSource::add_content_addressed_edge`.
+    pub fn add_content_addressed_edge<L: ToString>(&self, label: L) -> AddEdgeTraversal<'a, S> {
+        AddEdgeTraversal::new_content_addressed(
+            self.source,
+            label.to_string(),
+            Some(Rc::clone(&self.context)),
+        )
+    }
+
+    /// Writes every vertex/edge accumulated in this transaction in a single
+    /// atomic batch, alongside any secondary index entries for vertices
+    /// touched by it and the sequence-number advance from any ids it
+    /// consumed.
+    pub fn commit(self) -> Result<(), GraphError> {
+        let mut writes = self.context.borrow().pending_writes();
+        writes.extend(self.index_writes());
+        writes.push(self.source.context_write());
+        self.context.borrow_mut().resolve();
+        self.source.storage.write_batch(writes).map_err(GraphError::from)
+    }
+
+    /// Discards every vertex/edge accumulated in this transaction without
+    /// writing anything.
+    pub fn rollback(self) {
+        self.context.borrow_mut().resolve();
+    }
+
+    /// Index entries for every dirty vertex, one per property this source
+    /// has registered with `create_index`.
+    fn index_writes(&self) -> Vec<(Vec<u8>, Vec<u8>)> {
+        let indexes = self.source.indexed_properties();
+        if indexes.is_empty() {
+            return Vec::new();
+        }
+
+        self.context
+            .borrow()
+            .vertices
+            .values()
+            .filter(|entry| entry.dirty)
+            .flat_map(|entry| {
+                let vertex = &entry.entry;
+                indexes.iter().filter_map(move |prop| {
+                    vertex::indexed_value(vertex, prop).map(|value| {
+                        (
+                            crate::index_key(prop, value, vertex.id()).into_bytes(),
+                            crate::index_value(vertex.id()),
+                        )
+                    })
+                })
+            })
+            .collect()
+    }
+}
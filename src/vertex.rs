@@ -1,9 +1,14 @@
-use crate::db::PrefixSearchIterator;
-use crate::{create_vertex_key, DirtyEntry, TraversalContext};
-use rocksdb::{DBWithThreadMode, SingleThreaded};
+use crate::edge::{AdjacentVertexTraversal, Direction};
+use crate::query::VertexQuery;
+use crate::storage::Storage;
+use crate::{base32, TraversalContext};
 use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::hash_map::DefaultHasher;
 use std::collections::HashMap;
 use std::fmt::Debug;
+use std::hash::{Hash, Hasher};
+use std::rc::Rc;
 
 pub const DEFAULT_LABEL: &str = "vertex";
 pub const KEY_PREFIX: &str = "vtx_";
@@ -27,20 +32,54 @@ impl Vertex {
     pub fn id(&self) -> usize {
         self.id
     }
+
+    /// A content-addressed display identity derived from this vertex's
+    /// label and properties, base32-encoded. It's a pure function of the
+    /// vertex's content, so it stays stable across separate databases;
+    /// see `GraphTraversalSource::add_content_addressed_vertex`.
+    pub fn content_hash(&self) -> String {
+        base32::encode_id(content_address(&self.label, &self.prop))
+    }
+}
+
+/// Derives a stable id from a vertex's label and sorted properties, used
+/// by the content-addressed identity mode.
+pub(crate) fn content_address(label: &str, prop: &HashMap<String, String>) -> usize {
+    let mut entries: Vec<_> = prop.iter().collect();
+    entries.sort();
+
+    let mut hasher = DefaultHasher::new();
+    label.hash(&mut hasher);
+    for (key, value) in entries {
+        key.hash(&mut hasher);
+        value.hash(&mut hasher);
+    }
+    hasher.finish() as usize
+}
+
+/// Extracts the value that keys a secondary index entry for `prop`,
+/// special-casing the dedicated `label` field so `create_index("label")`
+/// works without it having to also live in `prop`.
+pub(crate) fn indexed_value<'v>(vertex: &'v Vertex, prop: &str) -> Option<&'v str> {
+    if prop == "label" {
+        Some(vertex.label.as_str())
+    } else {
+        vertex.prop.get(prop).map(String::as_str)
+    }
 }
 
-pub struct VertexTraversal<'a> {
-    pub(crate) prefix_search: PrefixSearchIterator<'a, DBWithThreadMode<SingleThreaded>>,
+pub struct VertexTraversal<'a, S: Storage> {
+    pub(crate) prefix_search: S::Iter<'a>,
     pub(crate) label: Option<&'a str>,
-    pub(crate) _context: TraversalContext<'a>,
+    pub(crate) context: Rc<RefCell<TraversalContext<'a, S>>>,
 }
 
-impl<'a> Iterator for VertexTraversal<'a> {
+impl<'a, S: Storage> Iterator for VertexTraversal<'a, S> {
     type Item = Vertex;
 
     fn next(&mut self) -> Option<Self::Item> {
         if let Some(label) = self.label {
-            while let Some((_, value)) = self.prefix_search.next() {
+            for (_, value) in self.prefix_search.by_ref() {
                 let value: Vertex = bincode::deserialize(&value).unwrap();
                 if value.label == label {
                     return Some(value);
@@ -54,28 +93,187 @@ impl<'a> Iterator for VertexTraversal<'a> {
     }
 }
 
-pub struct SingleVertexTraversal<'a> {
+impl<'a, S: Storage + 'a> VertexTraversal<'a, S> {
+    /// Traverses to the vertices reachable by an outgoing edge with the
+    /// specified label.
+    pub fn out(self, label: &'a str) -> AdjacentVertexTraversal<'a, S> {
+        let context = Rc::clone(&self.context);
+        AdjacentVertexTraversal::new(Box::new(self), Direction::Out, label, context)
+    }
+
+    /// Traverses to the vertices reachable by an incoming edge with the
+    /// specified label.
+    pub fn r#in(self, label: &'a str) -> AdjacentVertexTraversal<'a, S> {
+        let context = Rc::clone(&self.context);
+        AdjacentVertexTraversal::new(Box::new(self), Direction::In, label, context)
+    }
+
+    /// Traverses to the vertices reachable by an edge with the specified
+    /// label, regardless of direction.
+    pub fn both(self, label: &'a str) -> AdjacentVertexTraversal<'a, S> {
+        let context = Rc::clone(&self.context);
+        AdjacentVertexTraversal::new(Box::new(self), Direction::Both, label, context)
+    }
+
+    /// Starts a query pipeline, keeping only vertices with the given label.
+    /// If this traversal is an unfiltered scan over all vertices and
+    /// `create_index("label")` has been called, walks that index directly
+    /// instead of scanning every vertex in `vtx_`; see `indexed_source`.
+    pub fn has_label<L: ToString>(self, label: L) -> VertexQuery<'a> {
+        let label = label.to_string();
+        match self.indexed_source("label", &label) {
+            Some(source) => VertexQuery::new(source),
+            None => VertexQuery::new(Box::new(self)).has_label(label),
+        }
+    }
+
+    /// Starts a query pipeline, keeping only vertices with a matching
+    /// `key`/`value` property. If this traversal is an unfiltered scan
+    /// over all vertices and `create_index(key)` has been called, walks
+    /// that index directly instead of scanning every vertex in `vtx_`;
+    /// see `indexed_source`.
+    pub fn has<K: ToString, V: ToString>(self, key: K, value: V) -> VertexQuery<'a> {
+        let key = key.to_string();
+        let value = value.to_string();
+        match self.indexed_source(&key, &value) {
+            Some(source) => VertexQuery::new(source),
+            None => VertexQuery::new(Box::new(self)).has(key, value),
+        }
+    }
+
+    /// Returns a vertex source that walks the `index_prefix(prop, value)`
+    /// prefix directly, if `prop` has a secondary index and this traversal hasn't
+    /// already been narrowed by `vertices_with_label` (which filters on a
+    /// different criterion the index lookup can't account for). This is
+    /// the index-aware planning `has_label`/`has` fall back to a full
+    /// `vtx_` scan without.
+    fn indexed_source(
+        &self,
+        prop: &str,
+        value: &str,
+    ) -> Option<Box<dyn Iterator<Item = Vertex> + 'a>> {
+        if self.label.is_some() {
+            return None;
+        }
+
+        let context = self.context.borrow();
+        if !crate::indexed_properties_in_storage(context.storage)
+            .iter()
+            .any(|p| p == prop)
+        {
+            return None;
+        }
+        let prefix = crate::index_prefix(prop, value);
+        let prefix_search = context.storage.prefix_iter(prefix.as_bytes());
+        drop(context);
+
+        Some(Box::new(FindByTraversal {
+            prefix_search,
+            context: Rc::clone(&self.context),
+        }))
+    }
+
+    /// Starts a query pipeline, keeping only vertices matching an arbitrary
+    /// predicate.
+    pub fn r#where<F: Fn(&Vertex) -> bool + 'a>(self, predicate: F) -> VertexQuery<'a> {
+        VertexQuery::new(Box::new(self)).r#where(predicate)
+    }
+
+    /// Starts a query pipeline that drops vertices already seen earlier in
+    /// the pipeline.
+    pub fn dedup(self) -> VertexQuery<'a> {
+        VertexQuery::new(Box::new(self)).dedup()
+    }
+
+    /// Starts a query pipeline that stops the traversal after `n` vertices.
+    pub fn limit(self, n: usize) -> VertexQuery<'a> {
+        VertexQuery::new(Box::new(self)).limit(n)
+    }
+}
+
+pub struct AddVertexTraversal<'a, S: Storage> {
     pub(crate) id: Option<usize>,
-    pub(crate) context: TraversalContext<'a>,
+    pub(crate) context: Rc<RefCell<TraversalContext<'a, S>>>,
 }
 
-impl<'a> Iterator for SingleVertexTraversal<'a> {
+impl<'a, S: Storage> Iterator for AddVertexTraversal<'a, S> {
     type Item = Vertex;
 
     fn next(&mut self) -> Option<Self::Item> {
         let id = self.id.take()?;
-        let vertex = match self.context.vertices.get(&id) {
-            Some(x) => x.entry.clone(),
-            None => {
-                let key = create_vertex_key(id);
-                let bytes = self.context.database.get(key).unwrap().unwrap();
-                let vertex: Vertex = bincode::deserialize(&bytes).unwrap();
-                let entry = DirtyEntry::new(vertex.clone());
-                self.context.vertices.insert(id, entry);
-                vertex
+        let vertex = self.context.borrow().vertices.get(&id)?.entry.clone();
+        Some(vertex)
+    }
+}
+
+impl<'a, S: Storage> AddVertexTraversal<'a, S> {
+    /// Sets a property on the vertex being added. Only takes effect if
+    /// called before the write is committed: vertices added through
+    /// `GraphTraversalSource::add_vertex`/`add_vertex_with_label` commit
+    /// immediately, so use an explicit `Transaction` and call this before
+    /// `Transaction::commit` instead.
+    pub fn property<K: ToString, V: ToString>(self, key: K, value: V) -> Self {
+        if let Some(id) = self.id {
+            let mut context = self.context.borrow_mut();
+            if let Some(entry) = context.vertices.get_mut(&id) {
+                entry.entry.prop.insert(key.to_string(), value.to_string());
+                entry.dirty = true;
             }
-        };
+        }
+        self
+    }
+}
 
-        Some(vertex)
+pub struct VertexWithIdTraversal<'a, S: Storage> {
+    pub(crate) id: Option<usize>,
+    pub(crate) context: Rc<RefCell<TraversalContext<'a, S>>>,
+}
+
+impl<'a, S: Storage> Iterator for VertexWithIdTraversal<'a, S> {
+    type Item = Vertex;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let id = self.id.take()?;
+        crate::fetch_vertex(&self.context, id)
+    }
+}
+
+impl<'a, S: Storage + 'a> VertexWithIdTraversal<'a, S> {
+    /// Traverses to the vertices reachable by an outgoing edge with the
+    /// specified label.
+    pub fn out(self, label: &'a str) -> AdjacentVertexTraversal<'a, S> {
+        let context = Rc::clone(&self.context);
+        AdjacentVertexTraversal::new(Box::new(self), Direction::Out, label, context)
+    }
+
+    /// Traverses to the vertices reachable by an incoming edge with the
+    /// specified label.
+    pub fn r#in(self, label: &'a str) -> AdjacentVertexTraversal<'a, S> {
+        let context = Rc::clone(&self.context);
+        AdjacentVertexTraversal::new(Box::new(self), Direction::In, label, context)
+    }
+
+    /// Traverses to the vertices reachable by an edge with the specified
+    /// label, regardless of direction.
+    pub fn both(self, label: &'a str) -> AdjacentVertexTraversal<'a, S> {
+        let context = Rc::clone(&self.context);
+        AdjacentVertexTraversal::new(Box::new(self), Direction::Both, label, context)
+    }
+}
+
+/// Spawns a traversal over the vertices matched by a secondary index; see
+/// `GraphTraversalSource::create_index`/`find_by`.
+pub struct FindByTraversal<'a, S: Storage> {
+    pub(crate) prefix_search: S::Iter<'a>,
+    pub(crate) context: Rc<RefCell<TraversalContext<'a, S>>>,
+}
+
+impl<'a, S: Storage> Iterator for FindByTraversal<'a, S> {
+    type Item = Vertex;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (_, value) = self.prefix_search.next()?;
+        let id: usize = bincode::deserialize(&value).unwrap();
+        crate::fetch_vertex(&self.context, id)
     }
 }